@@ -0,0 +1,577 @@
+// Copyright 2023 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The Kafka message-set v2 record batch format.
+//!
+//! `PartitionData.records` is carried on the wire as an opaque [`bytes::Bytes`];
+//! this module turns those bytes into [`RecordBatch`]/[`Record`] values and
+//! back. The layout implemented here is the v2 format introduced by KIP-98:
+//! a fixed header followed by a length-prefixed, optionally compressed array of
+//! records that use zig-zag varint/varlong encoding for their variable-width
+//! fields. Compression is selected by the low three bits of the batch
+//! `attributes` and dispatched through [`Compression`], with each codec guarded
+//! by its own feature flag.
+//!
+//! Interop note: `Gzip` and `Zstd` use the same frame formats Kafka does and
+//! round-trip against real brokers and clients. `Snappy` and `Lz4`, however,
+//! currently use the raw Snappy block codec and the `lz4_flex` length-prefixed
+//! block codec rather than Kafka's xerial Snappy block framing and the LZ4
+//! frame format. Batches compressed with those two codecs round-trip against
+//! this crate but are **not** wire-compatible with other Kafka implementations
+//! yet; produce and consume them only between peers that both use this crate.
+
+use std::io;
+
+use bytes::Buf;
+use bytes::BufMut;
+use bytes::Bytes;
+
+use crate::codec::*;
+
+/// The only record batch magic byte this codec understands.
+const MAGIC: i8 = 2;
+
+/// The compression codec carried in the low three bits of the batch attributes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// No compression; the records payload is stored verbatim.
+    #[default]
+    None,
+    /// gzip, behind the `gzip` feature.
+    Gzip,
+    /// Snappy, behind the `snappy` feature. Uses the raw Snappy block codec,
+    /// not Kafka's xerial block framing, so it is not yet wire-compatible with
+    /// other Kafka implementations (see the module docs).
+    Snappy,
+    /// LZ4, behind the `lz4` feature. Uses the `lz4_flex` length-prefixed block
+    /// codec, not the LZ4 frame format Kafka expects, so it is not yet
+    /// wire-compatible with other Kafka implementations (see the module docs).
+    Lz4,
+    /// Zstandard (KIP-110), behind the `zstd` feature.
+    Zstd,
+}
+
+impl Compression {
+    /// The codec id as stored in the batch attributes.
+    fn id(self) -> i16 {
+        match self {
+            Compression::None => 0,
+            Compression::Gzip => 1,
+            Compression::Snappy => 2,
+            Compression::Lz4 => 3,
+            Compression::Zstd => 4,
+        }
+    }
+
+    fn from_attributes(attributes: i16) -> io::Result<Compression> {
+        match attributes & 0x7 {
+            0 => Ok(Compression::None),
+            1 => Ok(Compression::Gzip),
+            2 => Ok(Compression::Snappy),
+            3 => Ok(Compression::Lz4),
+            4 => Ok(Compression::Zstd),
+            codec => Err(err_record(format!("unknown compression codec {codec}"))),
+        }
+    }
+
+    /// Compress the records payload, or return it unchanged for
+    /// [`Compression::None`]. Codecs whose feature is disabled error out.
+    fn compress(self, payload: &[u8]) -> io::Result<Bytes> {
+        match self {
+            Compression::None => Ok(Bytes::copy_from_slice(payload)),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => {
+                use std::io::Write;
+                let mut encoder =
+                    flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+                encoder.write_all(payload)?;
+                Ok(Bytes::from(encoder.finish()?))
+            }
+            #[cfg(feature = "snappy")]
+            Compression::Snappy => {
+                let mut encoder = snap::raw::Encoder::new();
+                encoder
+                    .compress_vec(payload)
+                    .map(Bytes::from)
+                    .map_err(|e| err_record(e.to_string()))
+            }
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => Ok(Bytes::from(lz4_flex::block::compress_prepend_size(payload))),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => zstd::stream::encode_all(payload, 0)
+                .map(Bytes::from)
+                .map_err(|e| err_record(e.to_string())),
+            // Compiled out once every codec feature is on, at which point the
+            // arms above are exhaustive and this would be an unreachable pattern.
+            #[cfg(not(all(
+                feature = "gzip",
+                feature = "snappy",
+                feature = "lz4",
+                feature = "zstd"
+            )))]
+            other => Err(err_record(format!(
+                "compression codec {other:?} is not enabled"
+            ))),
+        }
+    }
+
+    /// Decompress the records payload, or return it unchanged for
+    /// [`Compression::None`]. Codecs whose feature is disabled error out.
+    fn decompress(self, payload: &[u8]) -> io::Result<Bytes> {
+        match self {
+            Compression::None => Ok(Bytes::copy_from_slice(payload)),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => {
+                use std::io::Read;
+                let mut decoder = flate2::read::GzDecoder::new(payload);
+                let mut out = vec![];
+                decoder.read_to_end(&mut out)?;
+                Ok(Bytes::from(out))
+            }
+            #[cfg(feature = "snappy")]
+            Compression::Snappy => {
+                let mut decoder = snap::raw::Decoder::new();
+                decoder
+                    .decompress_vec(payload)
+                    .map(Bytes::from)
+                    .map_err(|e| err_record(e.to_string()))
+            }
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => lz4_flex::block::decompress_size_prepended(payload)
+                .map(Bytes::from)
+                .map_err(|e| err_record(e.to_string())),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => zstd::stream::decode_all(payload)
+                .map(Bytes::from)
+                .map_err(|e| err_record(e.to_string())),
+            // Compiled out once every codec feature is on, at which point the
+            // arms above are exhaustive and this would be an unreachable pattern.
+            #[cfg(not(all(
+                feature = "gzip",
+                feature = "snappy",
+                feature = "lz4",
+                feature = "zstd"
+            )))]
+            other => Err(err_record(format!(
+                "compression codec {other:?} is not enabled"
+            ))),
+        }
+    }
+}
+
+/// A single record inside a [`RecordBatch`]. Offsets and timestamps are stored
+/// as deltas from the batch base on the wire; these fields hold the decoded
+/// deltas.
+#[derive(Debug, Default, Clone)]
+pub struct Record {
+    /// Per-record attributes; currently unused and always zero.
+    pub attributes: i8,
+    /// The record timestamp relative to the batch base timestamp.
+    pub timestamp_delta: i64,
+    /// The record offset relative to the batch base offset.
+    pub offset_delta: i32,
+    /// The record key, or `None` when the record has no key.
+    pub key: Option<Bytes>,
+    /// The record value, or `None` for a tombstone.
+    pub value: Option<Bytes>,
+    /// The record headers.
+    pub headers: Vec<Header>,
+}
+
+/// A record header: a non-null key and a nullable value.
+#[derive(Debug, Default, Clone)]
+pub struct Header {
+    pub key: String,
+    pub value: Option<Bytes>,
+}
+
+/// A decoded v2 record batch.
+#[derive(Debug, Clone)]
+pub struct RecordBatch {
+    /// The offset of the first record in the batch.
+    pub base_offset: i64,
+    /// The leader epoch of the partition when the batch was produced.
+    pub partition_leader_epoch: i32,
+    /// The compression codec the records payload is stored with.
+    pub compression: Compression,
+    /// The offset of the last record relative to the base offset.
+    pub last_offset_delta: i32,
+    /// The timestamp of the first record in the batch.
+    pub base_timestamp: i64,
+    /// The largest timestamp in the batch.
+    pub max_timestamp: i64,
+    /// The producer id, or -1 when idempotence is not in use.
+    pub producer_id: i64,
+    /// The producer epoch, or -1 when idempotence is not in use.
+    pub producer_epoch: i16,
+    /// The sequence number of the first record, or -1.
+    pub base_sequence: i32,
+    /// The full 16-bit attributes, whose low three bits are the codec.
+    pub attributes: i16,
+    /// The decoded records.
+    pub records: Vec<Record>,
+}
+
+impl Default for RecordBatch {
+    fn default() -> Self {
+        RecordBatch {
+            base_offset: 0,
+            partition_leader_epoch: -1,
+            compression: Compression::None,
+            last_offset_delta: 0,
+            base_timestamp: 0,
+            max_timestamp: 0,
+            producer_id: -1,
+            producer_epoch: -1,
+            base_sequence: -1,
+            attributes: 0,
+            records: vec![],
+        }
+    }
+}
+
+impl RecordBatch {
+    /// Decode a single record batch from `buf`, validating the magic byte and
+    /// the CRC-32C over the bytes following the CRC field.
+    pub fn decode<B: Buf>(buf: &mut B) -> io::Result<RecordBatch> {
+        let base_offset = Int64.decode(buf)?;
+        let batch_length = Int32.decode(buf)?;
+        if batch_length < 0 || (buf.remaining() as i64) < batch_length as i64 {
+            return Err(err_record("record batch length exceeds available bytes"));
+        }
+        // Everything after `batch_length` belongs to this batch; slice it off so
+        // the CRC can be validated over exactly the covered region.
+        let mut body = buf.copy_to_bytes(batch_length as usize);
+
+        let partition_leader_epoch = Int32.decode(&mut body)?;
+        let magic = Int8.decode(&mut body)?;
+        if magic != MAGIC {
+            return Err(err_record(format!(
+                "unsupported record batch magic {magic}, expected {MAGIC}"
+            )));
+        }
+        let crc = UInt32.decode(&mut body)?;
+        let checked = crc32c::crc32c(body.chunk());
+        if crc != checked {
+            return Err(err_record(format!(
+                "record batch CRC mismatch: stored {crc}, computed {checked}"
+            )));
+        }
+
+        let attributes = Int16.decode(&mut body)?;
+        let compression = Compression::from_attributes(attributes)?;
+        let last_offset_delta = Int32.decode(&mut body)?;
+        let base_timestamp = Int64.decode(&mut body)?;
+        let max_timestamp = Int64.decode(&mut body)?;
+        let producer_id = Int64.decode(&mut body)?;
+        let producer_epoch = Int16.decode(&mut body)?;
+        let base_sequence = Int32.decode(&mut body)?;
+        let count = Int32.decode(&mut body)?;
+
+        let payload = compression.decompress(body.chunk())?;
+        let mut payload = payload.as_ref();
+        // `count` is read straight off the wire; a malformed batch can advertise
+        // ~2^31 records while carrying a handful of bytes. Each record needs at
+        // least one byte, so bound the pre-allocation by the bytes available.
+        let mut records = Vec::with_capacity(count.clamp(0, payload.len() as i32) as usize);
+        for _ in 0..count.max(0) {
+            records.push(decode_record(&mut payload)?);
+        }
+
+        Ok(RecordBatch {
+            base_offset,
+            partition_leader_epoch,
+            compression,
+            last_offset_delta,
+            base_timestamp,
+            max_timestamp,
+            producer_id,
+            producer_epoch,
+            base_sequence,
+            attributes,
+            records,
+        })
+    }
+
+    /// Encode this batch, recomputing the batch length and the CRC-32C.
+    pub fn encode<B: BufMut>(&self, buf: &mut B) -> io::Result<()> {
+        let mut payload = vec![];
+        for record in &self.records {
+            encode_record(&mut payload, record)?;
+        }
+        let payload = self.compression.compress(&payload)?;
+
+        // Assemble the CRC-covered region, then the part that precedes the CRC.
+        let attributes = (self.attributes & !0x7) | self.compression.id();
+        let mut covered = vec![];
+        Int16.encode(&mut covered, attributes)?;
+        Int32.encode(&mut covered, self.last_offset_delta)?;
+        Int64.encode(&mut covered, self.base_timestamp)?;
+        Int64.encode(&mut covered, self.max_timestamp)?;
+        Int64.encode(&mut covered, self.producer_id)?;
+        Int16.encode(&mut covered, self.producer_epoch)?;
+        Int32.encode(&mut covered, self.base_sequence)?;
+        Int32.encode(&mut covered, self.records.len() as i32)?;
+        covered.put_slice(&payload);
+
+        let crc = crc32c::crc32c(&covered);
+
+        let mut prefix = vec![];
+        Int32.encode(&mut prefix, self.partition_leader_epoch)?;
+        Int8.encode(&mut prefix, MAGIC)?;
+        UInt32.encode(&mut prefix, crc)?;
+
+        Int64.encode(buf, self.base_offset)?;
+        Int32.encode(buf, (prefix.len() + covered.len()) as i32)?;
+        buf.put_slice(&prefix);
+        buf.put_slice(&covered);
+        Ok(())
+    }
+}
+
+fn decode_record(buf: &mut &[u8]) -> io::Result<Record> {
+    let length = read_varint(buf)?;
+    if length < 0 || (buf.len() as i64) < length as i64 {
+        return Err(err_record("record length exceeds available bytes"));
+    }
+    let attributes = Int8.decode(buf)?;
+    let timestamp_delta = read_varlong(buf)?;
+    let offset_delta = read_varint(buf)?;
+    let key = read_varint_bytes(buf)?;
+    let value = read_varint_bytes(buf)?;
+    let header_count = read_varint(buf)?;
+    // Bound the pre-allocation by the bytes left (each header needs ≥1 byte) so
+    // a wire-supplied count cannot drive an unbounded allocation.
+    let mut headers = Vec::with_capacity(header_count.clamp(0, buf.len() as i32) as usize);
+    for _ in 0..header_count.max(0) {
+        let key = read_varint_bytes(buf)?
+            .ok_or_else(|| err_record("record header key must not be null"))?;
+        let key = String::from_utf8(key.to_vec())
+            .map_err(|e| err_record(format!("record header key is not valid UTF-8: {e}")))?;
+        let value = read_varint_bytes(buf)?;
+        headers.push(Header { key, value });
+    }
+    Ok(Record {
+        attributes,
+        timestamp_delta,
+        offset_delta,
+        key,
+        value,
+        headers,
+    })
+}
+
+fn encode_record(buf: &mut Vec<u8>, record: &Record) -> io::Result<()> {
+    let mut body = vec![];
+    Int8.encode(&mut body, record.attributes)?;
+    write_varlong(&mut body, record.timestamp_delta);
+    write_varint(&mut body, record.offset_delta);
+    write_varint_bytes(&mut body, record.key.as_deref());
+    write_varint_bytes(&mut body, record.value.as_deref());
+    write_varint(&mut body, record.headers.len() as i32);
+    for header in &record.headers {
+        write_varint_bytes(&mut body, Some(header.key.as_bytes()));
+        write_varint_bytes(&mut body, header.value.as_deref());
+    }
+    write_varint(buf, body.len() as i32);
+    buf.put_slice(&body);
+    Ok(())
+}
+
+fn read_varint_bytes(buf: &mut &[u8]) -> io::Result<Option<Bytes>> {
+    let length = read_varint(buf)?;
+    if length < 0 {
+        return Ok(None);
+    }
+    let length = length as usize;
+    if buf.len() < length {
+        return Err(err_record("varint-prefixed bytes exceed available input"));
+    }
+    let out = Bytes::copy_from_slice(&buf[..length]);
+    buf.advance(length);
+    Ok(Some(out))
+}
+
+fn write_varint_bytes(buf: &mut Vec<u8>, value: Option<&[u8]>) {
+    match value {
+        None => write_varint(buf, -1),
+        Some(bytes) => {
+            write_varint(buf, bytes.len() as i32);
+            buf.put_slice(bytes);
+        }
+    }
+}
+
+/// Decode a zig-zag encoded varint (up to 32 bits).
+fn read_varint(buf: &mut &[u8]) -> io::Result<i32> {
+    let value = read_unsigned_varint(buf, 5)?;
+    Ok(((value >> 1) as i32) ^ -((value & 1) as i32))
+}
+
+/// Decode a zig-zag encoded varlong (up to 64 bits).
+fn read_varlong(buf: &mut &[u8]) -> io::Result<i64> {
+    let value = read_unsigned_varint(buf, 10)?;
+    Ok(((value >> 1) as i64) ^ -((value & 1) as i64))
+}
+
+fn read_unsigned_varint(buf: &mut &[u8], max_bytes: u32) -> io::Result<u64> {
+    let mut value = 0u64;
+    let mut shift = 0u32;
+    loop {
+        if buf.is_empty() {
+            return Err(err_record("unexpected end of input while reading varint"));
+        }
+        let byte = buf[0];
+        buf.advance(1);
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+        shift += 7;
+        if shift >= max_bytes * 7 {
+            return Err(err_record("varint is too long"));
+        }
+    }
+}
+
+/// Encode an `i32` as a zig-zag varint.
+fn write_varint(buf: &mut Vec<u8>, value: i32) {
+    write_unsigned_varint(buf, ((value << 1) ^ (value >> 31)) as u32 as u64);
+}
+
+/// Encode an `i64` as a zig-zag varlong.
+fn write_varlong(buf: &mut Vec<u8>, value: i64) {
+    write_unsigned_varint(buf, ((value << 1) ^ (value >> 63)) as u64);
+}
+
+fn write_unsigned_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value & !0x7f != 0 {
+        buf.put_u8(((value & 0x7f) | 0x80) as u8);
+        value >>= 7;
+    }
+    buf.put_u8(value as u8);
+}
+
+fn err_record(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_batch() -> RecordBatch {
+        RecordBatch {
+            base_offset: 42,
+            partition_leader_epoch: 7,
+            last_offset_delta: 1,
+            base_timestamp: 1_600_000_000_000,
+            max_timestamp: 1_600_000_000_050,
+            producer_id: 123,
+            producer_epoch: 4,
+            base_sequence: 9,
+            records: vec![
+                Record {
+                    timestamp_delta: 0,
+                    offset_delta: 0,
+                    key: Some(Bytes::from_static(b"k0")),
+                    value: Some(Bytes::from_static(b"v0")),
+                    headers: vec![Header {
+                        key: "h".to_string(),
+                        value: Some(Bytes::from_static(b"hv")),
+                    }],
+                    ..Default::default()
+                },
+                Record {
+                    timestamp_delta: 50,
+                    offset_delta: 1,
+                    key: None,
+                    value: None,
+                    headers: vec![],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn assert_batch_eq(a: &RecordBatch, b: &RecordBatch) {
+        assert_eq!(a.base_offset, b.base_offset);
+        assert_eq!(a.partition_leader_epoch, b.partition_leader_epoch);
+        assert_eq!(a.compression, b.compression);
+        assert_eq!(a.last_offset_delta, b.last_offset_delta);
+        assert_eq!(a.base_timestamp, b.base_timestamp);
+        assert_eq!(a.max_timestamp, b.max_timestamp);
+        assert_eq!(a.producer_id, b.producer_id);
+        assert_eq!(a.producer_epoch, b.producer_epoch);
+        assert_eq!(a.base_sequence, b.base_sequence);
+        assert_eq!(a.records.len(), b.records.len());
+        for (x, y) in a.records.iter().zip(&b.records) {
+            assert_eq!(x.timestamp_delta, y.timestamp_delta);
+            assert_eq!(x.offset_delta, y.offset_delta);
+            assert_eq!(x.key, y.key);
+            assert_eq!(x.value, y.value);
+            assert_eq!(x.headers.len(), y.headers.len());
+            for (hx, hy) in x.headers.iter().zip(&y.headers) {
+                assert_eq!(hx.key, hy.key);
+                assert_eq!(hx.value, hy.value);
+            }
+        }
+    }
+
+    #[test]
+    fn record_batch_round_trips() {
+        let batch = sample_batch();
+        let mut buf = vec![];
+        batch.encode(&mut buf).unwrap();
+        let decoded = RecordBatch::decode(&mut buf.as_slice()).unwrap();
+        assert_batch_eq(&batch, &decoded);
+    }
+
+    #[test]
+    fn record_batch_crc_mismatch_is_rejected() {
+        let mut buf = vec![];
+        sample_batch().encode(&mut buf).unwrap();
+        // Corrupt the last byte, which lies in the records payload inside the
+        // CRC-covered region.
+        let corrupt = buf.len() - 1;
+        buf[corrupt] ^= 0xff;
+        let err = RecordBatch::decode(&mut buf.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("CRC mismatch"));
+    }
+
+    #[test]
+    fn record_batch_truncation_is_rejected() {
+        let mut buf = vec![];
+        sample_batch().encode(&mut buf).unwrap();
+        buf.truncate(buf.len() - 4);
+        let err = RecordBatch::decode(&mut buf.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn varint_zigzag_round_trips() {
+        for value in [0, -1, 1, i32::MIN, i32::MAX, -12345, 67890] {
+            let mut buf = vec![];
+            write_varint(&mut buf, value);
+            assert_eq!(read_varint(&mut buf.as_slice()).unwrap(), value);
+        }
+        for value in [0i64, -1, 1, i64::MIN, i64::MAX, -1_600_000_000_000] {
+            let mut buf = vec![];
+            write_varlong(&mut buf, value);
+            assert_eq!(read_varlong(&mut buf.as_slice()).unwrap(), value);
+        }
+    }
+}
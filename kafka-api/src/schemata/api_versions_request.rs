@@ -15,6 +15,7 @@
 use std::io;
 
 use bytes::Buf;
+use bytes::BufMut;
 
 use crate::{codec::*, err_decode_message_null};
 
@@ -47,3 +48,18 @@ impl Decodable for ApiVersionsRequest {
         Ok(this)
     }
 }
+
+impl Encodable for ApiVersionsRequest {
+    fn encode<B: BufMut>(&self, buf: &mut B, version: i16) -> io::Result<()> {
+        if version >= 3 {
+            NullableString(true).encode(buf, self.client_software_name.as_str())?;
+        }
+        if version >= 3 {
+            NullableString(true).encode(buf, self.client_software_version.as_str())?;
+        }
+        if version >= 3 {
+            RawTaggedFieldList.encode(buf, self.unknown_tagged_fields.as_slice())?;
+        }
+        Ok(())
+    }
+}
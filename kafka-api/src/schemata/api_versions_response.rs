@@ -0,0 +1,114 @@
+// Copyright 2023 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io;
+
+use bytes::Buf;
+use bytes::BufMut;
+
+use crate::codec::*;
+
+// Version 1 adds throttle time to the response.
+//
+// Starting in version 2, on quota violation, brokers send out responses before
+// throttling.
+//
+// Version 3 is the first flexible version. Tagged fields are only supported in
+// the body but not in the header. The length of the header must not change in
+// order to guarantee the backward compatibility.
+
+#[derive(Debug, Default, Clone)]
+pub struct ApiVersionsResponse {
+    /// The top-level error code.
+    pub error_code: i16,
+    /// The APIs supported by the broker.
+    pub api_keys: Vec<ApiVersion>,
+    /// The duration in milliseconds for which the request was throttled due to a quota violation,
+    /// or zero if the request did not violate any quota.
+    pub throttle_time_ms: i32,
+    /// Unknown tagged fields.
+    pub unknown_tagged_fields: Vec<RawTaggedField>,
+}
+
+impl Encodable for ApiVersionsResponse {
+    fn encode<B: BufMut>(&self, buf: &mut B, version: i16) -> io::Result<()> {
+        Int16.encode(buf, self.error_code)?;
+        NullableArray(Struct(version), version >= 3).encode(buf, self.api_keys.as_slice())?;
+        if version >= 1 {
+            Int32.encode(buf, self.throttle_time_ms)?;
+        }
+        if version >= 3 {
+            RawTaggedFieldList.encode(buf, self.unknown_tagged_fields.as_slice())?;
+        }
+        Ok(())
+    }
+}
+
+impl Decodable for ApiVersionsResponse {
+    fn decode<B: Buf>(buf: &mut B, version: i16) -> io::Result<Self> {
+        let mut res = ApiVersionsResponse {
+            error_code: Int16.decode(buf)?,
+            api_keys: NullableArray(Struct(version), version >= 3)
+                .decode(buf)?
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+        if version >= 1 {
+            res.throttle_time_ms = Int32.decode(buf)?;
+        }
+        if version >= 3 {
+            res.unknown_tagged_fields = RawTaggedFieldList.decode(buf)?;
+        }
+        Ok(res)
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ApiVersion {
+    /// The API index.
+    pub api_key: i16,
+    /// The minimum supported version, inclusive.
+    pub min_version: i16,
+    /// The maximum supported version, inclusive.
+    pub max_version: i16,
+    /// Unknown tagged fields.
+    pub unknown_tagged_fields: Vec<RawTaggedField>,
+}
+
+impl Encodable for ApiVersion {
+    fn encode<B: BufMut>(&self, buf: &mut B, version: i16) -> io::Result<()> {
+        Int16.encode(buf, self.api_key)?;
+        Int16.encode(buf, self.min_version)?;
+        Int16.encode(buf, self.max_version)?;
+        if version >= 3 {
+            RawTaggedFieldList.encode(buf, self.unknown_tagged_fields.as_slice())?;
+        }
+        Ok(())
+    }
+}
+
+impl Decodable for ApiVersion {
+    fn decode<B: Buf>(buf: &mut B, version: i16) -> io::Result<Self> {
+        let mut res = ApiVersion {
+            api_key: Int16.decode(buf)?,
+            min_version: Int16.decode(buf)?,
+            max_version: Int16.decode(buf)?,
+            ..Default::default()
+        };
+        if version >= 3 {
+            res.unknown_tagged_fields = RawTaggedFieldList.decode(buf)?;
+        }
+        Ok(res)
+    }
+}
@@ -14,6 +14,7 @@
 
 use std::io;
 
+use bytes::Buf;
 use bytes::BufMut;
 
 use crate::{codec::*, err_encode_message_unsupported};
@@ -79,6 +80,56 @@ impl Encodable for FetchResponse {
     }
 }
 
+impl Decodable for FetchResponse {
+    fn decode<B: Buf>(buf: &mut B, version: i16) -> io::Result<Self> {
+        let mut res = FetchResponse::default();
+        if version >= 1 {
+            res.throttle_time_ms = Int32.decode(buf)?;
+        }
+        if version >= 7 {
+            res.error_code = Int16.decode(buf)?;
+            res.session_id = Int32.decode(buf)?;
+        }
+        res.responses = NullableArray(Struct(version), version >= 12)
+            .decode(buf)?
+            .unwrap_or_default();
+        if version >= 12 {
+            res.unknown_tagged_fields = RawTaggedFieldList.decode(buf)?;
+        }
+        Ok(res)
+    }
+}
+
+impl FetchResponse {
+    /// The response for `topic_id`, keyed by the schema's `topic_id` map key.
+    pub fn topic(&self, topic_id: uuid::Uuid) -> Option<&FetchableTopicResponse> {
+        self.responses.iter().find(|t| t.topic_id == topic_id)
+    }
+
+    /// The mutable response for `topic_id`.
+    pub fn topic_mut(&mut self, topic_id: uuid::Uuid) -> Option<&mut FetchableTopicResponse> {
+        self.responses.iter_mut().find(|t| t.topic_id == topic_id)
+    }
+
+    /// The partition `partition_index` of topic `topic_id`, if present.
+    pub fn partition(
+        &self,
+        topic_id: uuid::Uuid,
+        partition_index: i32,
+    ) -> Option<&PartitionData> {
+        self.topic(topic_id)?.partition(partition_index)
+    }
+
+    /// The mutable partition `partition_index` of topic `topic_id`, if present.
+    pub fn partition_mut(
+        &mut self,
+        topic_id: uuid::Uuid,
+        partition_index: i32,
+    ) -> Option<&mut PartitionData> {
+        self.topic_mut(topic_id)?.partition_mut(partition_index)
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct FetchableTopicResponse {
     /// The topic name.
@@ -107,6 +158,60 @@ impl Encodable for FetchableTopicResponse {
     }
 }
 
+impl Decodable for FetchableTopicResponse {
+    fn decode<B: Buf>(buf: &mut B, version: i16) -> io::Result<Self> {
+        let mut res = FetchableTopicResponse::default();
+        if version <= 12 {
+            res.topic = NullableString(version >= 12)
+                .decode(buf)?
+                .unwrap_or_default();
+        }
+        if version >= 13 {
+            res.topic_id = Uuid.decode(buf)?;
+        }
+        res.partitions = NullableArray(Struct(version), version >= 12)
+            .decode(buf)?
+            .unwrap_or_default();
+        if version >= 12 {
+            res.unknown_tagged_fields = RawTaggedFieldList.decode(buf)?;
+        }
+        Ok(res)
+    }
+}
+
+impl FetchableTopicResponse {
+    /// The partition keyed by the schema's `partition_index` map key.
+    pub fn partition(&self, index: i32) -> Option<&PartitionData> {
+        self.partitions.iter().find(|p| p.partition_index == index)
+    }
+
+    /// The mutable partition keyed by `partition_index`.
+    pub fn partition_mut(&mut self, index: i32) -> Option<&mut PartitionData> {
+        self.partitions
+            .iter_mut()
+            .find(|p| p.partition_index == index)
+    }
+
+    /// The partition for `index`, inserting a default one keyed by `index` when
+    /// absent. New partitions are appended so the encode order is preserved.
+    pub fn partition_entry(&mut self, index: i32) -> &mut PartitionData {
+        if let Some(pos) = self
+            .partitions
+            .iter()
+            .position(|p| p.partition_index == index)
+        {
+            return &mut self.partitions[pos];
+        }
+        self.partitions.push(PartitionData {
+            partition_index: index,
+            ..Default::default()
+        });
+        self.partitions
+            .last_mut()
+            .expect("just pushed a partition")
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct PartitionData {
     /// The topic name.
@@ -185,6 +290,54 @@ impl Encodable for PartitionData {
     }
 }
 
+impl Decodable for PartitionData {
+    fn decode<B: Buf>(buf: &mut B, version: i16) -> io::Result<Self> {
+        let mut res = PartitionData {
+            partition_index: Int32.decode(buf)?,
+            error_code: Int16.decode(buf)?,
+            high_watermark: Int64.decode(buf)?,
+            ..Default::default()
+        };
+        if version >= 4 {
+            res.last_stable_offset = Int64.decode(buf)?;
+        }
+        if version >= 5 {
+            res.log_start_offset = Int64.decode(buf)?;
+        }
+        if version >= 4 {
+            res.aborted_transactions =
+                NullableArray(Struct(version), version >= 12).decode(buf)?;
+        }
+        if version >= 11 {
+            res.preferred_read_replica = Int32.decode(buf)?;
+        }
+        res.records = NullableBytes(version >= 12).decode(buf)?.unwrap_or_default();
+        if version >= 12 {
+            // Unpack the tag-0/1/2 fields that `encode` folds into the list.
+            let mut unknown = vec![];
+            for field in RawTaggedFieldList.decode(buf)? {
+                match field.tag {
+                    0 => {
+                        let mut data = field.data.as_ref();
+                        res.diverging_epoch = Some(EpochEndOffset::decode(&mut data, version)?);
+                    }
+                    1 => {
+                        let mut data = field.data.as_ref();
+                        res.current_leader = Some(LeaderIdAndEpoch::decode(&mut data, version)?);
+                    }
+                    2 => {
+                        let mut data = field.data.as_ref();
+                        res.snapshot_id = Some(SnapshotId::decode(&mut data, version)?);
+                    }
+                    _ => unknown.push(field),
+                }
+            }
+            res.unknown_tagged_fields = unknown;
+        }
+        Ok(res)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct EpochEndOffset {
     pub epoch: i32,
@@ -215,6 +368,16 @@ impl Encodable for EpochEndOffset {
     }
 }
 
+impl Decodable for EpochEndOffset {
+    fn decode<B: Buf>(buf: &mut B, _version: i16) -> io::Result<Self> {
+        Ok(EpochEndOffset {
+            epoch: Int32.decode(buf)?,
+            end_offset: Int64.decode(buf)?,
+            unknown_tagged_fields: RawTaggedFieldList.decode(buf)?,
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct LeaderIdAndEpoch {
     /// The ID of the current leader or -1 if the leader is unknown.
@@ -247,6 +410,16 @@ impl Encodable for LeaderIdAndEpoch {
     }
 }
 
+impl Decodable for LeaderIdAndEpoch {
+    fn decode<B: Buf>(buf: &mut B, _version: i16) -> io::Result<Self> {
+        Ok(LeaderIdAndEpoch {
+            leader_id: Int32.decode(buf)?,
+            leader_epoch: Int32.decode(buf)?,
+            unknown_tagged_fields: RawTaggedFieldList.decode(buf)?,
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SnapshotId {
     pub end_offset: i64,
@@ -277,6 +450,16 @@ impl Encodable for SnapshotId {
     }
 }
 
+impl Decodable for SnapshotId {
+    fn decode<B: Buf>(buf: &mut B, _version: i16) -> io::Result<Self> {
+        Ok(SnapshotId {
+            end_offset: Int64.decode(buf)?,
+            epoch: Int32.decode(buf)?,
+            unknown_tagged_fields: RawTaggedFieldList.decode(buf)?,
+        })
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct AbortedTransaction {
     /// The producer id associated with the aborted transaction.
@@ -303,3 +486,111 @@ impl Encodable for AbortedTransaction {
         Ok(())
     }
 }
+
+impl Decodable for AbortedTransaction {
+    fn decode<B: Buf>(buf: &mut B, version: i16) -> io::Result<Self> {
+        let mut res = AbortedTransaction {
+            producer_id: Int64.decode(buf)?,
+            first_offset: Int64.decode(buf)?,
+            ..Default::default()
+        };
+        if version >= 12 {
+            res.unknown_tagged_fields = RawTaggedFieldList.decode(buf)?;
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+
+    use super::*;
+
+    #[test]
+    fn partition_data_tagged_fields_round_trip() {
+        // Version 12+ folds diverging_epoch/current_leader/snapshot_id into the
+        // tagged-field list as tags 0/1/2; decoding must unpack them again.
+        let version = 15;
+        let data = PartitionData {
+            partition_index: 3,
+            error_code: 0,
+            high_watermark: 100,
+            last_stable_offset: 90,
+            log_start_offset: 10,
+            diverging_epoch: Some(EpochEndOffset {
+                epoch: 5,
+                end_offset: 88,
+                ..Default::default()
+            }),
+            current_leader: Some(LeaderIdAndEpoch {
+                leader_id: 1,
+                leader_epoch: 6,
+                ..Default::default()
+            }),
+            snapshot_id: Some(SnapshotId {
+                end_offset: 42,
+                epoch: 2,
+                ..Default::default()
+            }),
+            aborted_transactions: Some(vec![]),
+            preferred_read_replica: -1,
+            records: bytes::Bytes::from_static(b"payload"),
+            ..Default::default()
+        };
+
+        let mut buf = BytesMut::new();
+        data.encode(&mut buf, version).unwrap();
+        let decoded = PartitionData::decode(&mut buf, version).unwrap();
+
+        assert_eq!(decoded.partition_index, data.partition_index);
+        assert_eq!(decoded.high_watermark, data.high_watermark);
+        assert_eq!(decoded.last_stable_offset, data.last_stable_offset);
+        assert_eq!(decoded.log_start_offset, data.log_start_offset);
+        assert_eq!(decoded.records, data.records);
+        let epoch = decoded.diverging_epoch.expect("diverging_epoch");
+        assert_eq!(epoch.epoch, 5);
+        assert_eq!(epoch.end_offset, 88);
+        let leader = decoded.current_leader.expect("current_leader");
+        assert_eq!(leader.leader_id, 1);
+        assert_eq!(leader.leader_epoch, 6);
+        let snapshot = decoded.snapshot_id.expect("snapshot_id");
+        assert_eq!(snapshot.end_offset, 42);
+        assert_eq!(snapshot.epoch, 2);
+    }
+
+    #[test]
+    fn fetch_response_round_trips() {
+        let version = 15;
+        let topic_id = uuid::Uuid::from_u128(0x1234_5678);
+        let response = FetchResponse {
+            throttle_time_ms: 5,
+            error_code: 0,
+            session_id: 77,
+            responses: vec![FetchableTopicResponse {
+                topic_id,
+                partitions: vec![PartitionData {
+                    partition_index: 0,
+                    high_watermark: 12,
+                    last_stable_offset: 12,
+                    aborted_transactions: Some(vec![]),
+                    preferred_read_replica: -1,
+                    records: bytes::Bytes::from_static(b"abc"),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let mut buf = BytesMut::new();
+        response.encode(&mut buf, version).unwrap();
+        let decoded = FetchResponse::decode(&mut buf, version).unwrap();
+
+        assert_eq!(decoded.throttle_time_ms, response.throttle_time_ms);
+        assert_eq!(decoded.session_id, response.session_id);
+        let partition = decoded.partition(topic_id, 0).expect("keyed lookup");
+        assert_eq!(partition.high_watermark, 12);
+        assert_eq!(partition.records, bytes::Bytes::from_static(b"abc"));
+    }
+}
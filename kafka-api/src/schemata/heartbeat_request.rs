@@ -51,4 +51,19 @@ impl Deserializable for HeartbeatRequest {
         }
         Ok(res)
     }
+}
+
+impl Serializable for HeartbeatRequest {
+    fn write<B: Writable>(&self, buf: &mut B, version: i16) -> io::Result<()> {
+        NullableString(version >= 4).encode(buf, self.group_id.as_str())?;
+        Int32.encode(buf, self.generation_id)?;
+        NullableString(version >= 4).encode(buf, self.member_id.as_str())?;
+        if version >= 3 {
+            NullableString(version >= 4).encode(buf, self.group_instance_id.as_deref())?;
+        }
+        if version >= 4 {
+            RawTaggedFieldList.encode(buf, self.unknown_tagged_fields.as_slice())?;
+        }
+        Ok(())
+    }
 }
\ No newline at end of file
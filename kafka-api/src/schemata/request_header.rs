@@ -15,6 +15,7 @@
 use std::io;
 
 use bytes::Buf;
+use bytes::BufMut;
 
 use crate::codec::*;
 
@@ -49,3 +50,18 @@ impl Decodable for RequestHeader {
         Ok(res)
     }
 }
+
+impl Encodable for RequestHeader {
+    fn encode<B: BufMut>(&self, buf: &mut B, version: i16) -> io::Result<()> {
+        Int16.encode(buf, self.request_api_key)?;
+        Int16.encode(buf, self.request_api_version)?;
+        Int32.encode(buf, self.correlation_id)?;
+        if version >= 1 {
+            NullableString(false).encode(buf, self.client_id.as_str())?;
+        }
+        if version >= 2 {
+            RawTaggedFieldList.encode(buf, self.unknown_tagged_fields.as_slice())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+// Copyright 2023 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The ApiVersions handshake.
+//!
+//! A peer advertises, per API key, the range of wire versions it supports; the
+//! two sides then agree on the highest version both understand. This module
+//! holds a [`SupportedApiVersions`] registry describing our own ranges, knows
+//! how to answer an `ApiVersionsRequest` with an [`ApiVersionsResponse`], and
+//! negotiates a concrete version against a peer's advertised ranges.
+
+use std::collections::BTreeMap;
+use std::io;
+
+use crate::schemata::api_versions_response::ApiVersion;
+use crate::schemata::api_versions_response::ApiVersionsResponse;
+use crate::schemata::request_header::RequestHeader;
+
+/// The error code returned when no mutually supported version exists.
+pub const UNSUPPORTED_VERSION: i16 = 35;
+
+/// An inclusive range of wire versions supported for a single API key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionRange {
+    pub min_version: i16,
+    pub max_version: i16,
+}
+
+impl VersionRange {
+    pub fn new(min_version: i16, max_version: i16) -> VersionRange {
+        VersionRange {
+            min_version,
+            max_version,
+        }
+    }
+
+    /// The highest version both ranges support, or `None` when they are
+    /// disjoint.
+    pub fn intersect(&self, other: &VersionRange) -> Option<i16> {
+        let min = self.min_version.max(other.min_version);
+        let max = self.max_version.min(other.max_version);
+        (min <= max).then_some(max)
+    }
+
+    /// Whether `version` falls inside this range.
+    pub fn contains(&self, version: i16) -> bool {
+        self.min_version <= version && version <= self.max_version
+    }
+}
+
+/// A registry mapping each API key to the range of versions we support. The
+/// wire representation stays a plain list; this keeps an ordered index for O(log
+/// n) lookup by key.
+#[derive(Debug, Default, Clone)]
+pub struct SupportedApiVersions {
+    ranges: BTreeMap<i16, VersionRange>,
+}
+
+impl SupportedApiVersions {
+    pub fn new() -> SupportedApiVersions {
+        SupportedApiVersions::default()
+    }
+
+    /// Register the supported range for an API key, replacing any previous
+    /// entry.
+    pub fn register(&mut self, api_key: i16, min_version: i16, max_version: i16) -> &mut Self {
+        self.ranges
+            .insert(api_key, VersionRange::new(min_version, max_version));
+        self
+    }
+
+    /// The range we support for `api_key`, if any.
+    pub fn range(&self, api_key: i16) -> Option<VersionRange> {
+        self.ranges.get(&api_key).copied()
+    }
+
+    /// Compute the highest version we and the peer both support for `api_key`,
+    /// returning an [`UNSUPPORTED_VERSION`] error when we have no range
+    /// registered for the key or the ranges do not overlap.
+    pub fn negotiate(&self, api_key: i16, peer: VersionRange) -> io::Result<i16> {
+        let ours = self
+            .range(api_key)
+            .ok_or_else(|| err_unsupported_version(api_key))?;
+        ours.intersect(&peer)
+            .ok_or_else(|| err_unsupported_version(api_key))
+    }
+
+    /// Negotiate every API key the peer advertised in its
+    /// [`ApiVersionsResponse`], skipping keys we do not support.
+    pub fn negotiate_all(&self, peer: &ApiVersionsResponse) -> BTreeMap<i16, i16> {
+        let mut negotiated = BTreeMap::new();
+        for advertised in &peer.api_keys {
+            let range = VersionRange::new(advertised.min_version, advertised.max_version);
+            if let Ok(version) = self.negotiate(advertised.api_key, range) {
+                negotiated.insert(advertised.api_key, version);
+            }
+        }
+        negotiated
+    }
+
+    /// Validate the version requested by an incoming [`RequestHeader`] against
+    /// our supported range, returning it when supported so dispatch can rely on
+    /// a negotiated version instead of a hardcoded constant.
+    pub fn accept(&self, header: &RequestHeader) -> io::Result<i16> {
+        match self.range(header.request_api_key) {
+            Some(range) if range.contains(header.request_api_version) => {
+                Ok(header.request_api_version)
+            }
+            _ => Err(err_unsupported_version(header.request_api_key)),
+        }
+    }
+
+    /// Build the [`ApiVersionsResponse`] that advertises these ranges in answer
+    /// to an `ApiVersionsRequest`. Keys are emitted in ascending order.
+    pub fn to_response(&self) -> ApiVersionsResponse {
+        let api_keys = self
+            .ranges
+            .iter()
+            .map(|(&api_key, range)| ApiVersion {
+                api_key,
+                min_version: range.min_version,
+                max_version: range.max_version,
+                unknown_tagged_fields: vec![],
+            })
+            .collect();
+        ApiVersionsResponse {
+            error_code: 0,
+            api_keys,
+            throttle_time_ms: 0,
+            unknown_tagged_fields: vec![],
+        }
+    }
+}
+
+fn err_unsupported_version(api_key: i16) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("no mutually supported version for api key {api_key} (error code {UNSUPPORTED_VERSION})"),
+    )
+}
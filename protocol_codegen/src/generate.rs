@@ -0,0 +1,343 @@
+// Copyright 2023 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Emits codec impls from a parsed [`Message`], mapping each spec type onto the
+//! crate's codec primitives in the exact shape the hand-written
+//! `PartitionData::encode` uses today. Responses get an `Encodable` impl and
+//! requests and headers a `Decodable` impl; no `Deserializable` impls are
+//! generated.
+
+use std::fmt::Write;
+
+use crate::spec::Field;
+use crate::spec::Message;
+use crate::spec::VersionRange;
+
+/// Render the Rust source for one message definition and all of its nested
+/// struct definitions.
+pub fn generate(message: &Message) -> String {
+    let mut out = String::new();
+    let mut structs = vec![];
+    collect_structs(&message.name, &message.fields, &mut structs);
+    for (name, fields) in structs {
+        emit_struct(&mut out, message, &name, fields);
+    }
+    out
+}
+
+/// A nested `[]Struct` or `Struct`-typed field becomes a top-level generated
+/// struct, mirroring how `EpochEndOffset`/`LeaderIdAndEpoch` are split out.
+fn collect_structs<'a>(
+    name: &str,
+    fields: &'a [Field],
+    acc: &mut Vec<(String, &'a [Field])>,
+) {
+    for field in fields {
+        if let Some(inner) = struct_type(&field.kind) {
+            collect_structs(inner, &field.fields, acc);
+        }
+    }
+    acc.push((name.to_string(), fields));
+}
+
+fn emit_struct(out: &mut String, message: &Message, name: &str, fields: &[Field]) {
+    let _ = writeln!(out, "#[derive(Debug, Default, Clone)]");
+    let _ = writeln!(out, "pub struct {name} {{");
+    for field in fields {
+        if field.map_key {
+            let _ = writeln!(out, "    /// The map key of this entry.");
+        }
+        let _ = writeln!(
+            out,
+            "    pub {}: {},",
+            snake_case(&field.name),
+            rust_type(field)
+        );
+    }
+    let _ = writeln!(out, "    /// Unknown tagged fields.");
+    let _ = writeln!(out, "    pub unknown_tagged_fields: Vec<RawTaggedField>,");
+    let _ = writeln!(out, "}}\n");
+
+    match message.kind {
+        crate::spec::MessageKind::Response => emit_encode(out, message, name, fields),
+        crate::spec::MessageKind::Request | crate::spec::MessageKind::Header => {
+            emit_decode(out, message, name, fields)
+        }
+    }
+}
+
+fn emit_encode(out: &mut String, message: &Message, name: &str, fields: &[Field]) {
+    let _ = writeln!(out, "impl Encodable for {name} {{");
+    let _ = writeln!(
+        out,
+        "    fn encode<B: BufMut>(&self, buf: &mut B, version: i16) -> io::Result<()> {{"
+    );
+    for field in fields.iter().filter(|f| f.tag.is_none()) {
+        let accessor = format!("self.{}", snake_case(&field.name));
+        let call = encode_call(message, field, &accessor);
+        emit_gated(out, field.versions, message.valid_versions, &call);
+    }
+    emit_tagged_encode(out, message, fields);
+    let _ = writeln!(out, "        Ok(())");
+    let _ = writeln!(out, "    }}");
+    let _ = writeln!(out, "}}\n");
+}
+
+/// Tagged fields are packed into a `RawTaggedFieldList` keyed by `tag`, exactly
+/// as `PartitionData::encode` does by hand.
+fn emit_tagged_encode(out: &mut String, message: &Message, fields: &[Field]) {
+    let tagged: Vec<&Field> = fields.iter().filter(|f| f.tag.is_some()).collect();
+    let flexible = !message.flexible_versions.is_empty();
+    if !flexible {
+        return;
+    }
+    let lo = message.flexible_versions.min;
+    let _ = writeln!(out, "        if version >= {lo} {{");
+    if tagged.is_empty() {
+        let _ = writeln!(
+            out,
+            "            RawTaggedFieldList.encode(buf, self.unknown_tagged_fields.as_slice())?;"
+        );
+    } else {
+        let _ = writeln!(out, "            let mut unknown_tagged_fields = vec![];");
+        for field in &tagged {
+            let snake = snake_case(&field.name);
+            let tag = field.tag.unwrap();
+            let _ = writeln!(out, "            if let Some({snake}) = &self.{snake} {{");
+            let _ = writeln!(
+                out,
+                "                unknown_tagged_fields.push(RawTaggedField {{"
+            );
+            let _ = writeln!(out, "                    tag: {tag},");
+            let _ = writeln!(
+                out,
+                "                    data: Struct(version).encode_alloc({snake})?,"
+            );
+            let _ = writeln!(out, "                }})");
+            let _ = writeln!(out, "            }}");
+        }
+        let _ = writeln!(
+            out,
+            "            unknown_tagged_fields.append(&mut self.unknown_tagged_fields.clone());"
+        );
+        let _ = writeln!(
+            out,
+            "            RawTaggedFieldList.encode(buf, &unknown_tagged_fields)?;"
+        );
+    }
+    let _ = writeln!(out, "        }}");
+}
+
+fn emit_decode(out: &mut String, message: &Message, name: &str, fields: &[Field]) {
+    let _ = writeln!(out, "impl Decodable for {name} {{");
+    let _ = writeln!(
+        out,
+        "    fn decode<B: Buf>(buf: &mut B, version: i16) -> io::Result<Self> {{"
+    );
+    let _ = writeln!(out, "        let mut res = {name}::default();");
+    for field in fields.iter().filter(|f| f.tag.is_none()) {
+        let call = decode_call(message, field);
+        let assign = format!("res.{} = {call};", snake_case(&field.name));
+        emit_gated(out, field.versions, message.valid_versions, &assign);
+    }
+    let flexible = !message.flexible_versions.is_empty();
+    if flexible {
+        let lo = message.flexible_versions.min;
+        let _ = writeln!(out, "        if version >= {lo} {{");
+        let _ = writeln!(
+            out,
+            "            res.unknown_tagged_fields = RawTaggedFieldList.decode(buf)?;"
+        );
+        let _ = writeln!(out, "        }}");
+    }
+    let _ = writeln!(out, "        Ok(res)");
+    let _ = writeln!(out, "    }}");
+    let _ = writeln!(out, "}}\n");
+}
+
+/// Emit `stmt` under the `if version >= N` / `if version <= M` guard implied by
+/// intersecting the field range with the message range; unconditional when the
+/// field spans every valid version.
+fn emit_gated(out: &mut String, field: VersionRange, message: VersionRange, stmt: &str) {
+    let range = field.intersect(&message);
+    let needs_lo = range.min > message.min;
+    let needs_hi = range.max < message.max;
+    let guard = match (needs_lo, needs_hi) {
+        (false, false) => None,
+        (true, false) => Some(format!("version >= {}", range.min)),
+        (false, true) => Some(format!("version <= {}", range.max)),
+        (true, true) => Some(format!(
+            "(version >= {} && version <= {})",
+            range.min, range.max
+        )),
+    };
+    match guard {
+        Some(guard) => {
+            let _ = writeln!(out, "        if {guard} {{");
+            let _ = writeln!(out, "            {stmt}");
+            let _ = writeln!(out, "        }}");
+        }
+        None => {
+            let _ = writeln!(out, "        {stmt}");
+        }
+    }
+}
+
+/// Map a spec type onto the codec primitive used to encode a value of that type.
+fn encode_call(message: &Message, field: &Field, accessor: &str) -> String {
+    let flexible = !message.flexible_versions.is_empty();
+    let flexible_expr = format!("version >= {}", message.flexible_versions.min);
+    let flexible_arg = if flexible { &flexible_expr } else { "false" };
+    match field.kind.as_str() {
+        "int8" => format!("Int8.encode(buf, {accessor})?;"),
+        "int16" => format!("Int16.encode(buf, {accessor})?;"),
+        "int32" => format!("Int32.encode(buf, {accessor})?;"),
+        "int64" => format!("Int64.encode(buf, {accessor})?;"),
+        "uuid" => format!("Uuid.encode(buf, {accessor})?;"),
+        "bool" => format!("Bool.encode(buf, {accessor})?;"),
+        "float64" => format!("Float64.encode(buf, {accessor})?;"),
+        "string" => format!("NullableString({flexible_arg}).encode(buf, {accessor}.as_str())?;"),
+        "bytes" => format!("NullableBytes({flexible_arg}).encode(buf, &{accessor})?;"),
+        other if other.starts_with("[]") => {
+            let inner = codec_inner(&other[2..], flexible_arg);
+            // `Option<Vec<_>>` borrows as a slice through `as_deref`, matching
+            // `PartitionData::aborted_transactions`; a plain `Vec` uses `as_slice`.
+            let borrow = if !field.nullable_versions.is_empty() {
+                "as_deref()"
+            } else {
+                "as_slice()"
+            };
+            format!("NullableArray({inner}, {flexible_arg}).encode(buf, {accessor}.{borrow})?;")
+        }
+        other => format!("Struct(version).encode(buf, &{accessor})?; // {other}"),
+    }
+}
+
+fn decode_call(message: &Message, field: &Field) -> String {
+    let flexible = !message.flexible_versions.is_empty();
+    let flexible_expr = format!("version >= {}", message.flexible_versions.min);
+    let flexible_arg = if flexible { &flexible_expr } else { "false" };
+    match field.kind.as_str() {
+        "int8" => "Int8.decode(buf)?".to_string(),
+        "int16" => "Int16.decode(buf)?".to_string(),
+        "int32" => "Int32.decode(buf)?".to_string(),
+        "int64" => "Int64.decode(buf)?".to_string(),
+        "uuid" => "Uuid.decode(buf)?".to_string(),
+        "bool" => "Bool.decode(buf)?".to_string(),
+        "float64" => "Float64.decode(buf)?".to_string(),
+        "string" => format!("NullableString({flexible_arg}).decode(buf)?.unwrap_or_default()"),
+        "bytes" => format!("NullableBytes({flexible_arg}).decode(buf)?.unwrap_or_default()"),
+        other if other.starts_with("[]") => {
+            let inner = codec_inner(&other[2..], flexible_arg);
+            // A nullable array is typed `Option<Vec<_>>` (see `rust_type`) and
+            // keeps the decoded `Option`; a plain `Vec` defaults a null to empty.
+            if !field.nullable_versions.is_empty() {
+                format!("NullableArray({inner}, {flexible_arg}).decode(buf)?")
+            } else {
+                format!("NullableArray({inner}, {flexible_arg}).decode(buf)?.unwrap_or_default()")
+            }
+        }
+        _ => "Struct(version).decode(buf)?".to_string(),
+    }
+}
+
+/// The codec primitive for an array element type. `flexible_arg` is the array's
+/// own flexibility expression, threaded through so string/bytes elements stay
+/// compact inside a compact array.
+fn codec_inner(inner: &str, flexible_arg: &str) -> String {
+    match inner {
+        "int8" => "Int8".to_string(),
+        "int16" => "Int16".to_string(),
+        "int32" => "Int32".to_string(),
+        "int64" => "Int64".to_string(),
+        "uuid" => "Uuid".to_string(),
+        "bool" => "Bool".to_string(),
+        "float64" => "Float64".to_string(),
+        "string" => format!("NullableString({flexible_arg})"),
+        "bytes" => format!("NullableBytes({flexible_arg})"),
+        other if struct_type(other).is_some() => "Struct(version)".to_string(),
+        other => panic!("unsupported array element type `[]{other}`"),
+    }
+}
+
+/// The Rust field type for a spec type. An array that is nullable on any
+/// version, like `PartitionData::aborted_transactions`, and any tagged field
+/// become an `Option`.
+fn rust_type(field: &Field) -> String {
+    let base = match field.kind.as_str() {
+        "int8" => "i8".to_string(),
+        "int16" => "i16".to_string(),
+        "int32" => "i32".to_string(),
+        "int64" => "i64".to_string(),
+        "uuid" => "uuid::Uuid".to_string(),
+        "bool" => "bool".to_string(),
+        "float64" => "f64".to_string(),
+        "string" => "String".to_string(),
+        "bytes" => "bytes::Bytes".to_string(),
+        other if other.starts_with("[]") => format!("Vec<{}>", element_type(&other[2..])),
+        other => other.to_string(),
+    };
+    // A nullable array surfaces as `Option<Vec<_>>`, and a tagged struct field
+    // is carried as an `Option` so it can be packed into the tagged-field list
+    // only when present; scalars and strings keep their bare type and represent
+    // null via their sentinel/default.
+    if field.tag.is_some() || (!field.nullable_versions.is_empty() && field.kind.starts_with("[]"))
+    {
+        format!("Option<{base}>")
+    } else {
+        base
+    }
+}
+
+fn element_type(inner: &str) -> String {
+    match inner {
+        "int8" => "i8".to_string(),
+        "int16" => "i16".to_string(),
+        "int32" => "i32".to_string(),
+        "int64" => "i64".to_string(),
+        "uuid" => "uuid::Uuid".to_string(),
+        "bool" => "bool".to_string(),
+        "float64" => "f64".to_string(),
+        "string" => "String".to_string(),
+        "bytes" => "bytes::Bytes".to_string(),
+        // Anything else is a nested struct, carried by its generated type name.
+        other => other.to_string(),
+    }
+}
+
+/// The inner struct name for a `Struct` or `[]Struct` field, if any.
+fn struct_type(kind: &str) -> Option<&str> {
+    let inner = kind.strip_prefix("[]").unwrap_or(kind);
+    match inner {
+        "int8" | "int16" | "int32" | "int64" | "uuid" | "bool" | "float64" | "string"
+        | "bytes" => None,
+        _ => Some(inner),
+    }
+}
+
+/// Convert a schema `camelCase`/`PascalCase` name to the crate's `snake_case`.
+fn snake_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, ch) in name.chars().enumerate() {
+        if ch.is_ascii_uppercase() {
+            if i != 0 {
+                out.push('_');
+            }
+            out.push(ch.to_ascii_lowercase());
+        } else {
+            out.push(ch);
+        }
+    }
+    out
+}
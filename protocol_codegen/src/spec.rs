@@ -0,0 +1,218 @@
+// Copyright 2023 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deserialization of the official Kafka `*.json` message definition files.
+
+use serde::Deserialize;
+
+/// A top-level message definition, e.g. `FetchResponse.json`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Message {
+    /// Absent on header schemas, which then parse with `0`.
+    #[serde(default)]
+    pub api_key: i16,
+    #[serde(rename = "type")]
+    pub kind: MessageKind,
+    pub name: String,
+    pub valid_versions: VersionRange,
+    pub flexible_versions: VersionRange,
+    pub fields: Vec<Field>,
+}
+
+/// Whether the message is a request or a response. Headers declare themselves
+/// as `header` and carry no `apiKey`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageKind {
+    Request,
+    Response,
+    Header,
+}
+
+/// A single field, possibly nesting further `fields` when its type is a struct
+/// or an array of structs.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Field {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub versions: VersionRange,
+    #[serde(default)]
+    pub nullable_versions: VersionRange,
+    #[serde(default)]
+    pub tag: Option<i32>,
+    #[serde(default)]
+    pub map_key: bool,
+    #[serde(default)]
+    pub fields: Vec<Field>,
+}
+
+/// An inclusive version range parsed from the schema notation: `"0-15"`,
+/// `"12+"`, a bare `"3"`, or `"none"` (the empty range).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionRange {
+    pub min: i16,
+    pub max: i16,
+}
+
+impl VersionRange {
+    /// The empty range, used as the default for optional `*Versions` keys.
+    pub const NONE: VersionRange = VersionRange { min: 1, max: 0 };
+
+    pub fn is_empty(&self) -> bool {
+        self.min > self.max
+    }
+
+    /// The largest range representable, used as the open end of `"N+"`.
+    fn open_end() -> i16 {
+        i16::MAX
+    }
+
+    /// The overlap of two ranges, or the empty range when they are disjoint.
+    pub fn intersect(&self, other: &VersionRange) -> VersionRange {
+        VersionRange {
+            min: self.min.max(other.min),
+            max: self.max.min(other.max),
+        }
+    }
+}
+
+impl Default for VersionRange {
+    fn default() -> Self {
+        VersionRange::NONE
+    }
+}
+
+impl<'de> Deserialize<'de> for VersionRange {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let raw = String::deserialize(deserializer)?;
+        raw.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl std::str::FromStr for VersionRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s == "none" {
+            return Ok(VersionRange::NONE);
+        }
+        if let Some(min) = s.strip_suffix('+') {
+            let min = min
+                .parse()
+                .map_err(|e| format!("invalid version lower bound in {s:?}: {e}"))?;
+            return Ok(VersionRange {
+                min,
+                max: VersionRange::open_end(),
+            });
+        }
+        if let Some((min, max)) = s.split_once('-') {
+            let min = min
+                .parse()
+                .map_err(|e| format!("invalid version lower bound in {s:?}: {e}"))?;
+            let max = max
+                .parse()
+                .map_err(|e| format!("invalid version upper bound in {s:?}: {e}"))?;
+            return Ok(VersionRange { min, max });
+        }
+        let v = s
+            .parse()
+            .map_err(|e| format!("invalid version {s:?}: {e}"))?;
+        Ok(VersionRange { min: v, max: v })
+    }
+}
+
+/// Parse a schema file after stripping the `//` comments and trailing commas
+/// that Kafka's definition files use but strict JSON forbids.
+pub fn parse(contents: &str) -> serde_json::Result<Message> {
+    serde_json::from_str(&strip_comments(contents))
+}
+
+fn strip_comments(contents: &str) -> String {
+    let mut out = String::with_capacity(contents.len());
+    for line in contents.lines() {
+        // Comments only ever start a token in these files, so a simple scan that
+        // ignores `//` inside string literals is sufficient.
+        let mut in_string = false;
+        let mut prev = '\0';
+        let mut chars = line.char_indices().peekable();
+        let mut cut = None;
+        while let Some((idx, ch)) = chars.next() {
+            if in_string {
+                if ch == '"' && prev != '\\' {
+                    in_string = false;
+                }
+            } else if ch == '"' {
+                in_string = true;
+            } else if ch == '/' && matches!(chars.peek(), Some((_, '/'))) {
+                cut = Some(idx);
+                break;
+            }
+            prev = ch;
+        }
+        match cut {
+            Some(idx) => out.push_str(&line[..idx]),
+            None => out.push_str(line),
+        }
+        out.push('\n');
+    }
+    strip_trailing_commas(&out)
+}
+
+fn strip_trailing_commas(contents: &str) -> String {
+    let mut out = String::with_capacity(contents.len());
+    let mut chars = contents.chars().peekable();
+    let mut in_string = false;
+    let mut prev = '\0';
+    while let Some(ch) = chars.next() {
+        if in_string {
+            out.push(ch);
+            if ch == '"' && prev != '\\' {
+                in_string = false;
+            }
+            prev = ch;
+            continue;
+        }
+        if ch == '"' {
+            in_string = true;
+            out.push(ch);
+            prev = ch;
+            continue;
+        }
+        if ch == ',' {
+            // Look past whitespace for a closing bracket.
+            let mut lookahead = chars.clone();
+            let mut next = None;
+            for peeked in lookahead.by_ref() {
+                if !peeked.is_whitespace() {
+                    next = Some(peeked);
+                    break;
+                }
+            }
+            if matches!(next, Some('}') | Some(']')) {
+                prev = ch;
+                continue;
+            }
+        }
+        out.push(ch);
+        prev = ch;
+    }
+    out
+}